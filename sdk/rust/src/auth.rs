@@ -0,0 +1,131 @@
+//! Per-host authentication tokens for cache-server and source requests,
+//! modeled on Deno's `DENO_AUTH_TOKENS`.
+
+use crate::base64;
+
+/// A credential to attach as an `Authorization` header.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AuthCredential {
+    /// `Authorization: Bearer <token>`.
+    Bearer(String),
+    /// `Authorization: Basic <base64(user:password)>`.
+    Basic { user: String, password: String },
+}
+
+/// An authentication token scoped to a host, matched exactly or as a
+/// suffix for subdomains.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AuthToken {
+    host: String,
+    credential: AuthCredential,
+}
+
+impl AuthToken {
+    /// Create a token that applies to `host` and its subdomains.
+    pub fn new(host: impl Into<String>, credential: AuthCredential) -> Self {
+        AuthToken {
+            host: host.into(),
+            credential,
+        }
+    }
+
+    fn matches_host(&self, host: &str) -> bool {
+        host.eq_ignore_ascii_case(&self.host)
+            || host
+                .to_ascii_lowercase()
+                .ends_with(&format!(".{}", self.host.to_ascii_lowercase()))
+    }
+
+    fn header_value(&self) -> String {
+        match &self.credential {
+            AuthCredential::Bearer(token) => format!("Bearer {token}"),
+            AuthCredential::Basic { user, password } => {
+                format!("Basic {}", base64::encode(format!("{user}:{password}").as_bytes()))
+            }
+        }
+    }
+}
+
+/// Parse the `FETCHURL_AUTH_TOKENS` environment variable value: a
+/// semicolon-separated list of `token@host` (bearer) or
+/// `user:password@host` (basic) entries.
+pub fn parse_auth_tokens(value: &str) -> Vec<AuthToken> {
+    value
+        .split(';')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .filter_map(parse_one_token)
+        .collect()
+}
+
+fn parse_one_token(entry: &str) -> Option<AuthToken> {
+    let (credential_part, host) = entry.rsplit_once('@')?;
+    let credential = match credential_part.split_once(':') {
+        Some((user, password)) => AuthCredential::Basic {
+            user: user.to_string(),
+            password: password.to_string(),
+        },
+        None => AuthCredential::Bearer(credential_part.to_string()),
+    };
+    Some(AuthToken::new(host, credential))
+}
+
+/// Push the `Authorization` header for `host` onto `headers`, if a token
+/// matches — never pushes a token scoped to a different host.
+pub(crate) fn apply(tokens: &[AuthToken], host: &str, headers: &mut Vec<(String, String)>) {
+    if let Some(token) = tokens.iter().find(|t| t.matches_host(host)) {
+        headers.push(("Authorization".to_string(), token.header_value()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_bearer_and_basic() {
+        let tokens = parse_auth_tokens("secret123@cache.example.com; alice:hunter2@private.example.org");
+        assert_eq!(
+            tokens,
+            vec![
+                AuthToken::new("cache.example.com", AuthCredential::Bearer("secret123".to_string())),
+                AuthToken::new(
+                    "private.example.org",
+                    AuthCredential::Basic {
+                        user: "alice".to_string(),
+                        password: "hunter2".to_string(),
+                    }
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_matches_subdomain_not_unrelated_host() {
+        let token = AuthToken::new("example.com", AuthCredential::Bearer("tok".to_string()));
+        assert!(token.matches_host("example.com"));
+        assert!(token.matches_host("mirror.example.com"));
+        assert!(!token.matches_host("notexample.com"));
+        assert!(!token.matches_host("example.com.evil.org"));
+    }
+
+    #[test]
+    fn test_apply_does_not_leak_token_to_other_host() {
+        let tokens = vec![AuthToken::new("a.example.com", AuthCredential::Bearer("tok".to_string()))];
+        let mut headers = Vec::new();
+        apply(&tokens, "b.example.com", &mut headers);
+        assert!(headers.is_empty());
+    }
+
+    #[test]
+    fn test_basic_header_value() {
+        let token = AuthToken::new(
+            "example.com",
+            AuthCredential::Basic {
+                user: "alice".to_string(),
+                password: "hunter2".to_string(),
+            },
+        );
+        assert_eq!(token.header_value(), "Basic YWxpY2U6aHVudGVyMg==");
+    }
+}
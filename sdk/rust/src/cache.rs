@@ -0,0 +1,214 @@
+//! Local content-addressable cache store, checked before any network attempt.
+
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+use crate::{to_hex, HasherInner};
+
+/// A content-addressable object store, checked before any network attempt.
+///
+/// Objects are keyed by `{algo, hash}`. Because that key is a cryptographic
+/// hash of the content, stored objects are immutable and never need expiry.
+pub trait CacheStore: Send + Sync {
+    /// Open the cached object for `{algo, hash}` for reading, if present.
+    fn get(&self, algo: &str, hash: &str) -> io::Result<Option<Box<dyn Read + Send>>>;
+
+    /// Begin writing the object for `{algo, hash}`.
+    ///
+    /// The returned writer buffers to a temporary location; call
+    /// [`CacheWriter::finish`] once the full, verified object has been
+    /// written so it can be installed atomically.
+    fn put(&self, algo: &str, hash: &str) -> io::Result<Box<dyn CacheWriter>>;
+}
+
+/// A write handle for an in-progress cache entry.
+pub trait CacheWriter: Write {
+    /// Finish the write: re-hash the buffered data and, if it matches the
+    /// `{algo, hash}` the writer was opened for, rename it into place.
+    /// Mismatched data is discarded instead of being installed.
+    fn finish(self: Box<Self>) -> io::Result<()>;
+}
+
+/// A filesystem-backed [`CacheStore`].
+///
+/// Objects are stored under `{root}/{algo}/{first 2 hash chars}/{hash}`.
+/// Writes go to a temporary file in the same directory and are renamed into
+/// place only after the content re-hashes correctly, so a crash mid-write
+/// never leaves a corrupt object visible to [`get`](CacheStore::get).
+pub struct FsCacheStore {
+    root: PathBuf,
+}
+
+impl FsCacheStore {
+    /// Create a store rooted at `root`. The directory is created lazily on
+    /// the first write.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        FsCacheStore { root: root.into() }
+    }
+
+    fn object_path(&self, algo: &str, hash: &str) -> PathBuf {
+        let prefix_len = hash.len().min(2);
+        self.root.join(algo).join(&hash[..prefix_len]).join(hash)
+    }
+}
+
+impl CacheStore for FsCacheStore {
+    fn get(&self, algo: &str, hash: &str) -> io::Result<Option<Box<dyn Read + Send>>> {
+        match File::open(self.object_path(algo, hash)) {
+            Ok(file) => Ok(Some(Box::new(file))),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn put(&self, algo: &str, hash: &str) -> io::Result<Box<dyn CacheWriter>> {
+        let final_path = self.object_path(algo, hash);
+        let dir = final_path
+            .parent()
+            .expect("object path always has a parent")
+            .to_path_buf();
+        fs::create_dir_all(&dir)?;
+
+        let temp_path = temp_path_for(&dir, hash);
+        let file = File::create(&temp_path)?;
+        let hasher = HasherInner::new(algo).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("unsupported algorithm: {algo}"),
+            )
+        })?;
+
+        Ok(Box::new(FsCacheWriter {
+            file,
+            temp_path,
+            final_path,
+            hasher,
+            expected_hash: hash.to_string(),
+        }))
+    }
+}
+
+fn temp_path_for(dir: &Path, hash: &str) -> PathBuf {
+    dir.join(format!(".{hash}.{}.tmp", rand::random::<u64>()))
+}
+
+struct FsCacheWriter {
+    file: File,
+    temp_path: PathBuf,
+    final_path: PathBuf,
+    hasher: HasherInner,
+    expected_hash: String,
+}
+
+impl Write for FsCacheWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.file.write(buf)?;
+        self.hasher.update(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+impl CacheWriter for FsCacheWriter {
+    fn finish(self: Box<Self>) -> io::Result<()> {
+        let this = *self;
+        this.file.sync_all()?;
+
+        let actual = to_hex(&this.hasher.finalize());
+        if actual != this.expected_hash {
+            let _ = fs::remove_file(&this.temp_path);
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "cache write hash mismatch: expected {}, got {actual}",
+                    this.expected_hash
+                ),
+            ));
+        }
+
+        fs::rename(&this.temp_path, &this.final_path)
+    }
+}
+
+/// A [`Write`] wrapper that tees bytes into a [`CacheWriter`] as they pass
+/// through to the inner writer.
+///
+/// Wrap the writer passed to [`FetchSession::verifier`](crate::FetchSession::verifier)
+/// with this when [`FetchSession::cache_writer`](crate::FetchSession::cache_writer)
+/// returns one, so a successful download is simultaneously stored.
+pub struct CacheTee<W: Write> {
+    inner: W,
+    cache: Box<dyn CacheWriter>,
+}
+
+impl<W: Write> CacheTee<W> {
+    /// Wrap `inner`, also writing every byte into `cache`.
+    pub fn new(inner: W, cache: Box<dyn CacheWriter>) -> Self {
+        CacheTee { inner, cache }
+    }
+
+    /// Finish the cache write and return the inner writer.
+    pub fn finish(self) -> io::Result<W> {
+        self.cache.finish()?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for CacheTee<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.cache.write_all(&buf[..n])?;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use digest::Digest;
+
+    use super::*;
+
+    #[test]
+    fn test_fs_cache_store_roundtrip() {
+        let dir = std::env::temp_dir().join(format!("fetchurl-cache-test-{}", rand::random::<u64>()));
+        let store = FsCacheStore::new(&dir);
+
+        let data = b"hello world";
+        let hash = to_hex(&sha2::Sha256::digest(data));
+
+        assert!(store.get("sha256", &hash).unwrap().is_none());
+
+        let mut writer = store.put("sha256", &hash).unwrap();
+        writer.write_all(data).unwrap();
+        writer.finish().unwrap();
+
+        let mut reader = store.get("sha256", &hash).unwrap().unwrap();
+        let mut read_back = Vec::new();
+        reader.read_to_end(&mut read_back).unwrap();
+        assert_eq!(read_back, data);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_fs_cache_store_rejects_hash_mismatch() {
+        let dir = std::env::temp_dir().join(format!("fetchurl-cache-test-{}", rand::random::<u64>()));
+        let store = FsCacheStore::new(&dir);
+
+        let mut writer = store.put("sha256", "not-the-real-hash").unwrap();
+        writer.write_all(b"hello world").unwrap();
+        assert!(writer.finish().is_err());
+
+        assert!(store.get("sha256", "not-the-real-hash").unwrap().is_none());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -28,11 +28,20 @@
 //! }
 //! ```
 
+use std::collections::{HashMap, HashSet};
 use std::io::{self, Write};
 
 use digest::Digest;
 use rand::seq::SliceRandom;
 
+mod auth;
+mod base64;
+mod cache;
+mod signature;
+pub use auth::{AuthCredential, AuthToken, parse_auth_tokens};
+pub use cache::{CacheStore, CacheTee, CacheWriter, FsCacheStore};
+pub use signature::{RequestSigner, SigningKey};
+
 /// Errors returned by the fetchurl SDK.
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
@@ -40,9 +49,20 @@ pub enum Error {
     #[error("unsupported algorithm: {0}")]
     UnsupportedAlgorithm(String),
 
+    /// The expected hash isn't a well-formed lowercase hex digest of the
+    /// length `algo` produces — never passed on to a [`CacheStore`], since
+    /// it's used to build a filesystem path.
+    #[error("invalid {algo} hash: {hash}")]
+    InvalidHash { algo: String, hash: String },
+
     /// The content hash does not match the expected hash.
     #[error("hash mismatch: expected {expected}, got {actual}")]
     HashMismatch { expected: String, actual: String },
+
+    /// Followed more redirects than the session's configured limit, or
+    /// detected a redirect loop back to an already-visited URL.
+    #[error("too many redirects")]
+    TooManyRedirects,
 }
 
 /// Normalize a hash algorithm name per the fetchurl spec:
@@ -64,6 +84,26 @@ pub fn is_supported(algo: &str) -> bool {
     matches!(normalize_algo(algo).as_str(), "sha1" | "sha256" | "sha512")
 }
 
+/// Hex-digest length produced by a normalized, supported algorithm name.
+fn expected_hash_len(algo: &str) -> Option<usize> {
+    match algo {
+        "sha1" => Some(40),
+        "sha256" => Some(64),
+        "sha512" => Some(128),
+        _ => None,
+    }
+}
+
+/// Check that `hash` is a lowercase hex digest of the length `algo`
+/// produces. Used to reject hashes before they're spliced into a
+/// [`CacheStore`] filesystem path, where stray `/` or `..` would escape
+/// the configured root.
+fn is_valid_hash(algo: &str, hash: &str) -> bool {
+    expected_hash_len(algo).is_some_and(|len| {
+        hash.len() == len && hash.bytes().all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b))
+    })
+}
+
 /// Parse the `FETCHURL_SERVER` environment variable value (an RFC 8941 string list).
 pub fn parse_fetchurl_server(value: &str) -> Vec<String> {
     parse_sfv_string_list(value)
@@ -147,17 +187,144 @@ fn parse_sfv_string_list(input: &str) -> Vec<String> {
     results
 }
 
+/// Encode `bytes` as an RFC 8941 byte sequence: `:base64:`.
+pub fn encode_sfv_byte_sequence(bytes: &[u8]) -> String {
+    format!(":{}:", base64::encode(bytes))
+}
+
+/// Parse an RFC 8941 byte sequence (`:base64:`) back into raw bytes.
+pub fn parse_sfv_byte_sequence(s: &str) -> Option<Vec<u8>> {
+    base64::decode(s.strip_prefix(':')?.strip_suffix(':')?)
+}
+
+/// Decode a lowercase hex string into bytes.
+pub(crate) fn hex_decode(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
+
+/// Map a normalized algo name to its RFC 9530 digest algorithm identifier.
+/// `sha1` has no registered identifier and is intentionally unsupported.
+fn repr_digest_algo(algo: &str) -> Option<&'static str> {
+    match algo {
+        "sha256" => Some("sha-256"),
+        "sha512" => Some("sha-512"),
+        _ => None,
+    }
+}
+
+/// Parse the comma-separated `algo=value` entries of a `Repr-Digest` (or
+/// legacy `Digest`) header into `(algo, raw_sfv_value)` pairs.
+fn parse_digest_entries(value: &str) -> Vec<(String, String)> {
+    value
+        .split(',')
+        .filter_map(|part| {
+            let (algo, raw) = part.trim().split_once('=')?;
+            Some((algo.trim().to_string(), raw.trim().to_string()))
+        })
+        .collect()
+}
+
+// --- URL helpers ---
+
+/// Extract the host from a URL, stripping scheme, userinfo, port, and path.
+///
+/// Minimal best-effort parser in the same spirit as the hand-rolled SFV
+/// parsing above — good enough for matching [`AuthToken`] hosts, not a
+/// general-purpose URL library.
+pub(crate) fn url_host(url: &str) -> Option<&str> {
+    let after_scheme = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let end = after_scheme
+        .find(['/', '?', '#'])
+        .unwrap_or(after_scheme.len());
+    let authority = &after_scheme[..end];
+    let host_port = authority.rsplit_once('@').map_or(authority, |(_, rest)| rest);
+    let host = host_port.split(':').next().unwrap_or(host_port);
+    if host.is_empty() {
+        None
+    } else {
+        Some(host)
+    }
+}
+
+/// Extract the path (and query, if any) from a URL, for use as the
+/// `@path` derived component in [`RequestSigner::sign`].
+pub(crate) fn url_path(url: &str) -> &str {
+    let after_scheme = url.split_once("://").map_or(url, |(_, rest)| rest);
+    match after_scheme.find('/') {
+        Some(idx) => &after_scheme[idx..],
+        None => "/",
+    }
+}
+
+/// Resolve a redirect `location` against the URL it was seen on, the way an
+/// HTTP client would: absolute locations are used as-is, `/`-prefixed
+/// locations replace the path on the same scheme/authority, and anything
+/// else is relative to `base`'s directory.
+pub(crate) fn resolve_url(base: &str, location: &str) -> String {
+    if location.contains("://") {
+        return location.to_string();
+    }
+
+    let (scheme_authority, base_path) = match base.find("://") {
+        Some(idx) => {
+            let after = &base[idx + 3..];
+            let path_start = after.find('/').map_or(base.len(), |p| idx + 3 + p);
+            (&base[..path_start], &base[path_start..])
+        }
+        None => ("", base),
+    };
+
+    if location.starts_with('/') {
+        format!("{scheme_authority}{location}")
+    } else {
+        let dir = match base_path.rfind('/') {
+            Some(idx) => &base_path[..=idx],
+            None => "/",
+        };
+        format!("{scheme_authority}{dir}{location}")
+    }
+}
+
 // --- FetchAttempt ---
 
+/// What kind of attempt a [`FetchAttempt`] represents.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AttemptKind {
+    /// A synthetic attempt: check the local [`CacheStore`] before touching
+    /// the network at all.
+    Cache,
+    /// A fetchurl cache/proxy server from `FETCHURL_SERVER`.
+    Server,
+    /// A direct source URL.
+    Direct,
+}
+
 /// A single fetch attempt, describing the URL to request and headers to set.
 #[derive(Clone, Debug)]
 pub struct FetchAttempt {
+    id: u64,
     url: String,
     headers: Vec<(String, String)>,
+    kind: AttemptKind,
 }
 
 impl FetchAttempt {
-    /// The URL to make a GET request to.
+    /// Identifies this attempt within its session, for
+    /// [`FetchSession::report_success_for`]/[`FetchSession::report_failure_for`]
+    /// when racing attempts handed out by [`FetchSession::next_batch`].
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// The URL to make a GET request to. For [`AttemptKind::Cache`] this is
+    /// not an HTTP URL — call [`FetchSession::check_cache`] instead of
+    /// requesting it over the network.
     pub fn url(&self) -> &str {
         &self.url
     }
@@ -166,6 +333,11 @@ impl FetchAttempt {
     pub fn headers(&self) -> &[(String, String)] {
         &self.headers
     }
+
+    /// What kind of attempt this is.
+    pub fn kind(&self) -> AttemptKind {
+        self.kind
+    }
 }
 
 // --- FetchSession ---
@@ -185,6 +357,16 @@ pub struct FetchSession {
     hash: String,
     done: bool,
     success: bool,
+    carried: Option<(HasherInner, u64)>,
+    cache_store: Option<Box<dyn CacheStore>>,
+    cache_checked: bool,
+    visited: HashSet<String>,
+    redirect_count: usize,
+    max_redirects: usize,
+    next_id: u64,
+    in_flight: HashSet<u64>,
+    issued: HashMap<u64, FetchAttempt>,
+    request_signer: Option<RequestSigner>,
 }
 
 impl FetchSession {
@@ -193,21 +375,432 @@ impl FetchSession {
     /// - `algo`: hash algorithm name (e.g. `"sha256"`)
     /// - `hash`: expected hash in hex
     /// - `source_urls`: direct source URLs (tried after servers, in random order)
+    ///
+    /// To also check a local [`CacheStore`] before the network, use
+    /// [`FetchSession::builder`] instead.
     pub fn new(
         algo: &str,
         hash: &str,
         source_urls: &[impl AsRef<str>],
     ) -> Result<Self, Error> {
+        Self::builder(algo, hash, source_urls)?.build()
+    }
+
+    /// Start building a fetch session, with room for optional extras like a
+    /// [`CacheStore`].
+    pub fn builder(
+        algo: &str,
+        hash: &str,
+        source_urls: &[impl AsRef<str>],
+    ) -> Result<FetchSessionBuilder, Error> {
+        FetchSessionBuilder::new(algo, hash, source_urls)
+    }
+
+    /// Get the next attempt to try.
+    ///
+    /// Returns `None` when all attempts are exhausted or the session is
+    /// finished (after [`report_success`](Self::report_success) or
+    /// [`report_partial`](Self::report_partial)).
+    ///
+    /// If a [`CacheStore`] was configured, the very first attempt is a
+    /// synthetic [`AttemptKind::Cache`] check — pass it to
+    /// [`check_cache`](Self::check_cache) instead of making an HTTP request.
+    ///
+    /// If the HTTP request fails without writing any bytes, just call
+    /// `next_attempt()` again to try the next source.
+    pub fn next_attempt(&mut self) -> Option<FetchAttempt> {
+        if self.done {
+            return None;
+        }
+        if self.cache_store.is_some() && !self.cache_checked {
+            self.cache_checked = true;
+            let attempt = FetchAttempt {
+                id: self.alloc_id(),
+                url: format!("cache:{}:{}", self.algo, self.hash),
+                headers: Vec::new(),
+                kind: AttemptKind::Cache,
+            };
+            self.issued.insert(attempt.id, attempt.clone());
+            return Some(attempt);
+        }
+        if self.current >= self.attempts.len() {
+            return None;
+        }
+        let mut attempt = self.attempts[self.current].clone();
+        self.current += 1;
+        attempt.id = self.alloc_id();
+        self.visited.insert(attempt.url.clone());
+        self.issued.insert(attempt.id, attempt.clone());
+        Some(attempt)
+    }
+
+    /// Get up to `n` not-yet-tried attempts at once, for racing across
+    /// threads/tasks instead of trying one at a time.
+    ///
+    /// Biases the batch toward cache-server attempts before direct sources,
+    /// same as [`next_attempt`](Self::next_attempt)'s ordering. Each
+    /// returned attempt is tracked as in-flight until resolved with
+    /// [`report_success_for`](Self::report_success_for) or
+    /// [`report_failure_for`](Self::report_failure_for).
+    ///
+    /// If a [`CacheStore`] was configured and not yet checked, the batch is
+    /// just the single synthetic [`AttemptKind::Cache`] attempt — satisfy it
+    /// with [`check_cache`](Self::check_cache) before racing the network.
+    pub fn next_batch(&mut self, n: usize) -> Vec<FetchAttempt> {
+        if self.done || n == 0 {
+            return Vec::new();
+        }
+        let mut batch = Vec::new();
+        while batch.len() < n {
+            let Some(attempt) = self.next_attempt() else {
+                break;
+            };
+            let is_cache = attempt.kind == AttemptKind::Cache;
+            self.in_flight.insert(attempt.id);
+            batch.push(attempt);
+            if is_cache {
+                break;
+            }
+        }
+        batch
+    }
+
+    /// Report that the attempt with this id succeeded and verified. Stops
+    /// the session — call [`should_cancel`](Self::should_cancel) from any
+    /// other in-flight attempts and abandon them.
+    pub fn report_success_for(&mut self, id: u64) {
+        self.in_flight.remove(&id);
+        self.report_success();
+    }
+
+    /// Report that the attempt with this id failed (or was cancelled)
+    /// without writing any bytes, so it no longer counts as in-flight.
+    pub fn report_failure_for(&mut self, id: u64) {
+        self.in_flight.remove(&id);
+    }
+
+    /// Whether the in-flight attempt `id` (from [`next_batch`](Self::next_batch))
+    /// should be abandoned — true once another attempt has already succeeded
+    /// while `id` is still outstanding (hasn't been reported via
+    /// [`report_success_for`](Self::report_success_for) or
+    /// [`report_failure_for`](Self::report_failure_for)).
+    pub fn should_cancel(&self, id: u64) -> bool {
+        self.done && self.in_flight.contains(&id)
+    }
+
+    /// How many attempts handed out by [`next_batch`](Self::next_batch) are
+    /// still outstanding (not yet reported via
+    /// [`report_success_for`](Self::report_success_for) or
+    /// [`report_failure_for`](Self::report_failure_for)).
+    pub fn in_flight_count(&self) -> usize {
+        self.in_flight.len()
+    }
+
+    fn alloc_id(&mut self) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        id
+    }
+
+    /// Report an HTTP redirect (301/302/303/307/308) with the given
+    /// `Location` header value, observed on the attempt identified by `id`
+    /// (from [`next_attempt`](Self::next_attempt) or
+    /// [`next_batch`](Self::next_batch)).
+    ///
+    /// Resolves `location` against that attempt's URL (relative locations
+    /// are supported), then enqueues it as the *next* attempt. Headers —
+    /// including `X-Source-Urls` and any `Authorization` — carry over only
+    /// if the redirect stays on the same host. If a [`RequestSigner`] was
+    /// configured and the redirected attempt is a [`AttemptKind::Server`]
+    /// one, any carried-over `Signature-Input`/`Signature` headers are
+    /// dropped and recomputed for the new `@authority`/`@path`, since the
+    /// old signature no longer covers them.
+    ///
+    /// `id` is looked up among attempts already handed out, so this is
+    /// safe to call after [`next_batch`](Self::next_batch) has issued
+    /// several attempts at once — the redirect is always resolved against
+    /// the attempt that actually produced it, not whichever one happens to
+    /// be next in line.
+    ///
+    /// Returns [`Error::TooManyRedirects`] if this would exceed the
+    /// session's redirect limit (see
+    /// [`FetchSessionBuilder::max_redirects`]) or revisits a URL already
+    /// seen in this session, and finishes the session in that case.
+    pub fn report_redirect(&mut self, id: u64, location: &str) -> Result<(), Error> {
+        let Some(current) = self.issued.remove(&id) else {
+            return Ok(());
+        };
+
+        let resolved = resolve_url(&current.url, location);
+
+        self.redirect_count += 1;
+        if self.redirect_count > self.max_redirects || self.visited.contains(&resolved) {
+            self.done = true;
+            return Err(Error::TooManyRedirects);
+        }
+
+        let mut headers = if url_host(&current.url) == url_host(&resolved) {
+            current.headers.clone()
+        } else {
+            Vec::new()
+        };
+
+        if current.kind == AttemptKind::Server {
+            headers.retain(|(k, _)| {
+                !k.eq_ignore_ascii_case("signature-input") && !k.eq_ignore_ascii_case("signature")
+            });
+            if let Some(signer) = &self.request_signer {
+                if let Some(authority) = url_host(&resolved) {
+                    let (signature_input, signature) =
+                        signer.sign(authority, url_path(&resolved), &headers);
+                    headers.push(("Signature-Input".to_string(), signature_input));
+                    headers.push(("Signature".to_string(), signature));
+                }
+            }
+        }
+
+        self.attempts.insert(
+            self.current,
+            FetchAttempt {
+                id: 0,
+                url: resolved,
+                headers,
+                kind: current.kind,
+            },
+        );
+        Ok(())
+    }
+
+    /// Look up the expected object in the configured [`CacheStore`].
+    ///
+    /// Call this after [`next_attempt`](Self::next_attempt) returns an
+    /// [`AttemptKind::Cache`] attempt. `Ok(Some(reader))` means the object
+    /// is already present locally; copy it to the output and call
+    /// [`report_success`](Self::report_success) without touching the
+    /// network. `Ok(None)` or no configured store means continue the loop
+    /// as usual.
+    pub fn check_cache(&self) -> io::Result<Option<Box<dyn io::Read + Send>>> {
+        match &self.cache_store {
+            Some(store) => store.get(&self.algo, &self.hash),
+            None => Ok(None),
+        }
+    }
+
+    /// Open a writer that stores the object into the configured
+    /// [`CacheStore`] under this session's `{algo, hash}`.
+    ///
+    /// Tee the network response through this alongside
+    /// [`verifier`](Self::verifier) (e.g. with [`CacheTee`]) so that on
+    /// [`report_success`](Self::report_success) the verified bytes are
+    /// simultaneously written into the store. Returns `None` if no store is
+    /// configured.
+    pub fn cache_writer(&self) -> Option<io::Result<Box<dyn CacheWriter>>> {
+        self.cache_store
+            .as_ref()
+            .map(|store| store.put(&self.algo, &self.hash))
+    }
+
+    /// Check an RFC 9530 `Repr-Digest` (or legacy `Digest`) response header
+    /// against the expected hash, before streaming the body.
+    ///
+    /// Returns [`Error::HashMismatch`] immediately on a mismatch, so the
+    /// caller can move on to the next source without reading the body. If
+    /// the header is missing, or doesn't carry an entry for this session's
+    /// algorithm, returns `Ok(())` so the caller falls back to the usual
+    /// [`HashVerifier`] stream verification.
+    pub fn check_repr_digest(&self, headers: &[(String, String)]) -> Result<(), Error> {
+        let Some(sfv_algo) = repr_digest_algo(&self.algo) else {
+            return Ok(());
+        };
+
+        let Some(value) = headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case("repr-digest"))
+            .or_else(|| headers.iter().find(|(k, _)| k.eq_ignore_ascii_case("digest")))
+            .map(|(_, v)| v.as_str())
+        else {
+            return Ok(());
+        };
+
+        let Some(raw) = parse_digest_entries(value)
+            .into_iter()
+            .find(|(algo, _)| algo == sfv_algo)
+            .map(|(_, raw)| raw)
+        else {
+            return Ok(());
+        };
+
+        let (Some(actual), Some(expected)) =
+            (parse_sfv_byte_sequence(&raw), hex_decode(&self.hash))
+        else {
+            return Ok(());
+        };
+
+        if actual == expected {
+            Ok(())
+        } else {
+            Err(Error::HashMismatch {
+                expected: self.hash.clone(),
+                actual: to_hex(&actual),
+            })
+        }
+    }
+
+    /// Report that the current attempt succeeded. Stops the session.
+    pub fn report_success(&mut self) {
+        self.done = true;
+        self.success = true;
+    }
+
+    /// Report that bytes were already written to the output before a failure,
+    /// with no intention of resuming. Stops the session — no further
+    /// attempts since the output is tainted.
+    ///
+    /// To continue instead of restarting from scratch, use
+    /// [`resume_from`](Self::resume_from).
+    pub fn report_partial(&mut self) {
+        self.done = true;
+    }
+
+    /// Resume after a partial failure instead of giving up.
+    ///
+    /// Hands back the inner writer from `verifier` and keeps its running
+    /// hash alive in the session. The next attempt returned by
+    /// [`next_attempt`](Self::next_attempt) is augmented with a
+    /// `Range: bytes=N-` header, where `N` is [`HashVerifier::bytes_written`].
+    ///
+    /// The caller must check the response: a `206 Partial Content` with a
+    /// matching `Content-Range` start means the tail can be fed into the
+    /// [`HashVerifier`] returned by the next call to
+    /// [`verifier`](Self::verifier), which picks up the carried-over hash.
+    /// A `200 OK` means the server ignored the Range request and the body is
+    /// the full object again — call [`reset_resume`](Self::reset_resume)
+    /// before streaming it.
+    pub fn resume_from<W: Write>(&mut self, verifier: HashVerifier<W>) -> W {
+        let (inner, hasher, bytes_written) = verifier.into_parts();
+        if let Some(next) = self.attempts.get_mut(self.current) {
+            next.headers
+                .push(("Range".to_string(), format!("bytes={bytes_written}-")));
+        }
+        self.carried = Some((hasher, bytes_written));
+        inner
+    }
+
+    /// Discard any carried-over hash state from [`resume_from`](Self::resume_from).
+    ///
+    /// Call this when a resumed attempt answers `200 OK` instead of `206
+    /// Partial Content`: the server ignored the Range header, so the next
+    /// response body is the full object rather than just the tail.
+    pub fn reset_resume(&mut self) {
+        self.carried = None;
+    }
+
+    /// Whether the session completed with a successful download.
+    pub fn succeeded(&self) -> bool {
+        self.success
+    }
+
+    /// Create a [`HashVerifier`] wrapping the given writer.
+    ///
+    /// Pipe the HTTP response body through the verifier, then call
+    /// [`HashVerifier::finish`] to check the hash. If a prior attempt was
+    /// carried over via [`resume_from`](Self::resume_from), the returned
+    /// verifier continues that hash instead of starting a new one.
+    pub fn verifier<W: Write>(&mut self, writer: W) -> HashVerifier<W> {
+        match self.carried.take() {
+            Some((hasher, bytes_written)) => {
+                HashVerifier::resumed(hasher, bytes_written, self.hash.clone(), writer)
+            }
+            None => HashVerifier::new(&self.algo, &self.hash, writer),
+        }
+    }
+}
+
+// --- FetchSessionBuilder ---
+
+/// Default cap on redirects a session will follow; see
+/// [`FetchSessionBuilder::max_redirects`].
+const DEFAULT_MAX_REDIRECTS: usize = 10;
+
+/// `Want-Digest` preference sent on cache-server attempts, biased toward
+/// SHA-256 per RFC 9530.
+const WANT_DIGEST: &str = "sha-256=1, sha-512=0.9";
+
+/// Builds a [`FetchSession`] with optional extras beyond the bare
+/// constructor, such as a [`CacheStore`].
+pub struct FetchSessionBuilder {
+    algo: String,
+    hash: String,
+    source_urls: Vec<String>,
+    cache_store: Option<Box<dyn CacheStore>>,
+    auth_tokens: Option<Vec<AuthToken>>,
+    max_redirects: usize,
+    request_signer: Option<RequestSigner>,
+}
+
+impl FetchSessionBuilder {
+    fn new(algo: &str, hash: &str, source_urls: &[impl AsRef<str>]) -> Result<Self, Error> {
         let algo = normalize_algo(algo);
         if !is_supported(&algo) {
             return Err(Error::UnsupportedAlgorithm(algo));
         }
+        if !is_valid_hash(&algo, hash) {
+            return Err(Error::InvalidHash {
+                algo,
+                hash: hash.to_string(),
+            });
+        }
+        Ok(FetchSessionBuilder {
+            algo,
+            hash: hash.to_string(),
+            source_urls: source_urls.iter().map(|s| s.as_ref().to_string()).collect(),
+            cache_store: None,
+            auth_tokens: None,
+            max_redirects: DEFAULT_MAX_REDIRECTS,
+            request_signer: None,
+        })
+    }
+
+    /// Check `store` for the expected object before any network attempt.
+    pub fn cache_store(mut self, store: impl CacheStore + 'static) -> Self {
+        self.cache_store = Some(Box::new(store));
+        self
+    }
+
+    /// Use `tokens` for per-host `Authorization` headers instead of parsing
+    /// `FETCHURL_AUTH_TOKENS`.
+    pub fn auth_tokens(mut self, tokens: Vec<AuthToken>) -> Self {
+        self.auth_tokens = Some(tokens);
+        self
+    }
+
+    /// Cap the number of redirects [`FetchSession::report_redirect`] will
+    /// follow before returning [`Error::TooManyRedirects`]. Defaults to 10.
+    pub fn max_redirects(mut self, max_redirects: usize) -> Self {
+        self.max_redirects = max_redirects;
+        self
+    }
+
+    /// Sign every cache-server attempt with `signer`, attaching
+    /// `Signature-Input`/`Signature` headers (RFC 9421). Never applied to
+    /// direct source attempts.
+    pub fn request_signer(mut self, signer: RequestSigner) -> Self {
+        self.request_signer = Some(signer);
+        self
+    }
 
+    /// Finish building the session.
+    pub fn build(self) -> Result<FetchSession, Error> {
         let servers_env = std::env::var("FETCHURL_SERVER").unwrap_or_default();
         let servers = parse_fetchurl_server(&servers_env);
 
-        let source_header = if !source_urls.is_empty() {
-            Some(encode_source_urls(source_urls))
+        let auth_tokens = self.auth_tokens.unwrap_or_else(|| {
+            let raw = std::env::var("FETCHURL_AUTH_TOKENS").unwrap_or_default();
+            parse_auth_tokens(&raw)
+        });
+
+        let source_header = if !self.source_urls.is_empty() {
+            Some(encode_source_urls(&self.source_urls))
         } else {
             None
         };
@@ -217,83 +810,70 @@ impl FetchSession {
         // Servers first
         for server in servers {
             let base = server.trim_end_matches('/');
-            let url = format!("{base}/api/fetchurl/{algo}/{hash}");
+            let url = format!("{base}/api/fetchurl/{}/{}", self.algo, self.hash);
             let mut headers = Vec::new();
             if let Some(ref val) = source_header {
                 headers.push(("X-Source-Urls".to_string(), val.clone()));
             }
-            attempts.push(FetchAttempt { url, headers });
+            headers.push(("Want-Digest".to_string(), WANT_DIGEST.to_string()));
+            if let Some(host) = url_host(&url) {
+                auth::apply(&auth_tokens, host, &mut headers);
+            }
+            if let Some(signer) = &self.request_signer {
+                if let Some(authority) = url_host(&url) {
+                    let (signature_input, signature) = signer.sign(authority, url_path(&url), &headers);
+                    headers.push(("Signature-Input".to_string(), signature_input));
+                    headers.push(("Signature".to_string(), signature));
+                }
+            }
+            attempts.push(FetchAttempt {
+                id: 0,
+                url,
+                headers,
+                kind: AttemptKind::Server,
+            });
         }
 
         // Direct sources (shuffled per spec)
-        let mut direct: Vec<String> = source_urls
-            .iter()
-            .map(|s| s.as_ref().to_string())
-            .collect();
+        let mut direct = self.source_urls;
         direct.shuffle(&mut rand::thread_rng());
         for url in direct {
+            let mut headers = Vec::new();
+            if let Some(host) = url_host(&url) {
+                auth::apply(&auth_tokens, host, &mut headers);
+            }
             attempts.push(FetchAttempt {
+                id: 0,
                 url,
-                headers: Vec::new(),
+                headers,
+                kind: AttemptKind::Direct,
             });
         }
 
         Ok(FetchSession {
             attempts,
             current: 0,
-            algo,
-            hash: hash.to_string(),
+            algo: self.algo,
+            hash: self.hash,
             done: false,
             success: false,
+            carried: None,
+            cache_store: self.cache_store,
+            cache_checked: false,
+            visited: HashSet::new(),
+            redirect_count: 0,
+            max_redirects: self.max_redirects,
+            next_id: 0,
+            in_flight: HashSet::new(),
+            issued: HashMap::new(),
+            request_signer: self.request_signer,
         })
     }
-
-    /// Get the next attempt to try.
-    ///
-    /// Returns `None` when all attempts are exhausted or the session is
-    /// finished (after [`report_success`](Self::report_success) or
-    /// [`report_partial`](Self::report_partial)).
-    ///
-    /// If the HTTP request fails without writing any bytes, just call
-    /// `next_attempt()` again to try the next source.
-    pub fn next_attempt(&mut self) -> Option<FetchAttempt> {
-        if self.done || self.current >= self.attempts.len() {
-            return None;
-        }
-        let attempt = self.attempts[self.current].clone();
-        self.current += 1;
-        Some(attempt)
-    }
-
-    /// Report that the current attempt succeeded. Stops the session.
-    pub fn report_success(&mut self) {
-        self.done = true;
-        self.success = true;
-    }
-
-    /// Report that bytes were already written to the output before a failure.
-    /// Stops the session — no further attempts since the output is tainted.
-    pub fn report_partial(&mut self) {
-        self.done = true;
-    }
-
-    /// Whether the session completed with a successful download.
-    pub fn succeeded(&self) -> bool {
-        self.success
-    }
-
-    /// Create a [`HashVerifier`] wrapping the given writer.
-    ///
-    /// Pipe the HTTP response body through the verifier, then call
-    /// [`HashVerifier::finish`] to check the hash.
-    pub fn verifier<W: Write>(&self, writer: W) -> HashVerifier<W> {
-        HashVerifier::new(&self.algo, &self.hash, writer)
-    }
 }
 
 // --- Hasher ---
 
-enum HasherInner {
+pub(crate) enum HasherInner {
     Sha1(sha1::Sha1),
     Sha256(sha2::Sha256),
     Sha512(sha2::Sha512),
@@ -326,7 +906,7 @@ impl HasherInner {
     }
 }
 
-fn to_hex(bytes: &[u8]) -> String {
+pub(crate) fn to_hex(bytes: &[u8]) -> String {
     bytes.iter().map(|b| format!("{b:02x}")).collect()
 }
 
@@ -353,11 +933,29 @@ impl<W: Write> HashVerifier<W> {
         }
     }
 
+    /// Build a verifier that continues a hash carried over from a previous,
+    /// partially-written attempt (see [`FetchSession::resume_from`]).
+    fn resumed(hasher: HasherInner, bytes_written: u64, expected_hash: String, inner: W) -> Self {
+        HashVerifier {
+            inner,
+            hasher,
+            expected_hash,
+            bytes_written,
+        }
+    }
+
     /// Number of bytes written to the inner writer so far.
     pub fn bytes_written(&self) -> u64 {
         self.bytes_written
     }
 
+    /// Tear down the verifier, returning the inner writer along with enough
+    /// state to continue the hash elsewhere (used by
+    /// [`FetchSession::resume_from`]).
+    fn into_parts(self) -> (W, HasherInner, u64) {
+        (self.inner, self.hasher, self.bytes_written)
+    }
+
     /// Finalize the hash and verify it matches the expected value.
     ///
     /// Returns the inner writer on success, or [`Error::HashMismatch`] on failure.
@@ -481,6 +1079,24 @@ mod tests {
         assert!(matches!(err, Err(Error::UnsupportedAlgorithm(_))));
     }
 
+    #[test]
+    fn test_session_rejects_malformed_hash() {
+        // Wrong length for sha256.
+        let err = FetchSession::new("sha256", "deadbeef", &["http://src"]);
+        assert!(matches!(err, Err(Error::InvalidHash { .. })));
+
+        // Right length, but not hex — and in particular not a path
+        // traversal sequence that could escape a CacheStore root.
+        let traversal = format!("../../../../../../../../../tmp/evil{}", "a".repeat(29));
+        assert_eq!(traversal.len(), 64);
+        let err = FetchSession::new("sha256", &traversal, &["http://src"]);
+        assert!(matches!(err, Err(Error::InvalidHash { .. })));
+
+        // Uppercase hex is rejected too — the spec requires lowercase.
+        let err = FetchSession::new("sha256", &sha256_hex(b"test").to_uppercase(), &["http://src"]);
+        assert!(matches!(err, Err(Error::InvalidHash { .. })));
+    }
+
     #[test]
     fn test_session_attempt_ordering() {
         let hash = sha256_hex(b"test");
@@ -544,6 +1160,38 @@ mod tests {
         assert!(session.next_attempt().is_none());
     }
 
+    #[test]
+    fn test_resume_from_carries_hash_and_adds_range_header() {
+        let data = b"hello world";
+        let hash = sha256_hex(data);
+        unsafe { std::env::set_var("FETCHURL_SERVER", "") };
+        let mut session = FetchSession::new("sha256", &hash, &["http://src1", "http://src2"])
+            .unwrap();
+
+        let _first = session.next_attempt().unwrap();
+        let mut output = Vec::new();
+        {
+            let mut verifier = session.verifier(&mut output);
+            verifier.write_all(&data[..6]).unwrap();
+            session.resume_from(verifier);
+        }
+
+        let next = session.next_attempt().unwrap();
+        assert_eq!(
+            next.headers()
+                .iter()
+                .find(|(k, _)| k == "Range")
+                .map(|(_, v)| v.as_str()),
+            Some("bytes=6-")
+        );
+
+        let mut verifier = session.verifier(&mut output);
+        verifier.write_all(&data[6..]).unwrap();
+        assert_eq!(verifier.bytes_written(), data.len() as u64);
+        verifier.finish().unwrap();
+        assert_eq!(output, data);
+    }
+
     #[test]
     fn test_session_server_has_source_header() {
         let hash = sha256_hex(b"test");
@@ -567,4 +1215,339 @@ mod tests {
         assert!(parsed.contains(&"http://src1".to_string()));
         assert!(parsed.contains(&"http://src2".to_string()));
     }
+
+    #[test]
+    fn test_sfv_byte_sequence_roundtrip() {
+        let data = b"some digest bytes";
+        let encoded = encode_sfv_byte_sequence(data);
+        assert!(encoded.starts_with(':') && encoded.ends_with(':'));
+        assert_eq!(parse_sfv_byte_sequence(&encoded).unwrap(), data);
+    }
+
+    #[test]
+    fn test_resolve_url_relative_and_absolute() {
+        assert_eq!(
+            resolve_url("https://a.com/dir/file", "other"),
+            "https://a.com/dir/other"
+        );
+        assert_eq!(
+            resolve_url("https://a.com/dir/file", "/root"),
+            "https://a.com/root"
+        );
+        assert_eq!(
+            resolve_url("https://a.com/dir/file", "https://b.com/x"),
+            "https://b.com/x"
+        );
+    }
+
+    #[test]
+    fn test_report_redirect_same_host_keeps_headers() {
+        let hash = sha256_hex(b"test");
+        unsafe { std::env::set_var("FETCHURL_SERVER", "") };
+        let mut session = FetchSession::builder("sha256", &hash, &["https://src.example.com/file"])
+            .unwrap()
+            .auth_tokens(vec![AuthToken::new(
+                "src.example.com",
+                AuthCredential::Bearer("tok".to_string()),
+            )])
+            .build()
+            .unwrap();
+
+        let first = session.next_attempt().unwrap();
+        assert!(!first.headers().is_empty());
+        session.report_redirect(first.id(), "/moved/file").unwrap();
+
+        let next = session.next_attempt().unwrap();
+        assert_eq!(next.url(), "https://src.example.com/moved/file");
+        assert_eq!(next.headers(), first.headers());
+    }
+
+    #[test]
+    fn test_report_redirect_cross_host_drops_headers() {
+        let hash = sha256_hex(b"test");
+        unsafe { std::env::set_var("FETCHURL_SERVER", "\"http://cache\"") };
+        let mut session =
+            FetchSession::new("sha256", &hash, &["https://src.example.com/file"]).unwrap();
+
+        let first = session.next_attempt().unwrap();
+        assert!(!first.headers().is_empty());
+        session
+            .report_redirect(first.id(), "https://mirror.example.net/file")
+            .unwrap();
+
+        let next = session.next_attempt().unwrap();
+        assert_eq!(next.url(), "https://mirror.example.net/file");
+        assert!(next.headers().is_empty());
+    }
+
+    #[test]
+    fn test_report_redirect_loop_errors() {
+        let hash = sha256_hex(b"test");
+        unsafe { std::env::set_var("FETCHURL_SERVER", "") };
+        let mut session =
+            FetchSession::new("sha256", &hash, &["https://src.example.com/a"]).unwrap();
+
+        let first = session.next_attempt().unwrap();
+        session
+            .report_redirect(first.id(), "https://src.example.com/b")
+            .unwrap();
+        let second = session.next_attempt().unwrap();
+        let err = session
+            .report_redirect(second.id(), "https://src.example.com/a")
+            .unwrap_err();
+        assert!(matches!(err, Error::TooManyRedirects));
+    }
+
+    #[test]
+    fn test_server_attempt_has_want_digest() {
+        let hash = sha256_hex(b"test");
+        unsafe { std::env::set_var("FETCHURL_SERVER", "\"http://cache\"") };
+        let mut session = FetchSession::new("sha256", &hash, &["http://src"]).unwrap();
+
+        let attempt = session.next_attempt().unwrap();
+        assert_eq!(
+            attempt
+                .headers()
+                .iter()
+                .find(|(k, _)| k == "Want-Digest")
+                .map(|(_, v)| v.as_str()),
+            Some("sha-256=1, sha-512=0.9")
+        );
+    }
+
+    #[test]
+    fn test_check_repr_digest_match() {
+        let data = b"hello world";
+        let hash = sha256_hex(data);
+        unsafe { std::env::set_var("FETCHURL_SERVER", "") };
+        let session = FetchSession::new("sha256", &hash, &["http://src"]).unwrap();
+
+        let digest_b64 = crate::base64::encode(&sha2::Sha256::digest(data));
+        let header = format!("sha-256=:{digest_b64}:");
+        assert!(session.check_repr_digest(&[("Repr-Digest".to_string(), header)]).is_ok());
+    }
+
+    #[test]
+    fn test_check_repr_digest_mismatch() {
+        let hash = sha256_hex(b"hello world");
+        unsafe { std::env::set_var("FETCHURL_SERVER", "") };
+        let session = FetchSession::new("sha256", &hash, &["http://src"]).unwrap();
+
+        let wrong_b64 = crate::base64::encode(&sha2::Sha256::digest(b"wrong"));
+        let header = format!("sha-256=:{wrong_b64}:");
+        let err = session
+            .check_repr_digest(&[("Repr-Digest".to_string(), header)])
+            .unwrap_err();
+        assert!(matches!(err, Error::HashMismatch { .. }));
+    }
+
+    #[test]
+    fn test_check_repr_digest_missing_header_falls_back_ok() {
+        let hash = sha256_hex(b"hello world");
+        unsafe { std::env::set_var("FETCHURL_SERVER", "") };
+        let session = FetchSession::new("sha256", &hash, &["http://src"]).unwrap();
+
+        assert!(session.check_repr_digest(&[]).is_ok());
+    }
+
+    #[test]
+    fn test_cache_store_hit_then_miss_then_populated_by_success() {
+        use std::io::Read as _;
+
+        let data = b"hello cached world";
+        let hash = sha256_hex(data);
+        let dir = std::env::temp_dir().join(format!("fetchurl-session-cache-test-{}", rand::random::<u64>()));
+        unsafe { std::env::set_var("FETCHURL_SERVER", "") };
+
+        // First session: nothing cached yet, so the synthetic Cache attempt
+        // is a miss and the caller must fall through to the network.
+        let mut session = FetchSession::builder("sha256", &hash, &["http://src"])
+            .unwrap()
+            .cache_store(FsCacheStore::new(&dir))
+            .build()
+            .unwrap();
+
+        let cache_attempt = session.next_attempt().unwrap();
+        assert_eq!(cache_attempt.kind(), AttemptKind::Cache);
+        assert!(session.check_cache().unwrap().is_none());
+
+        let network_attempt = session.next_attempt().unwrap();
+        assert_eq!(network_attempt.url(), "http://src");
+
+        // Simulate a successful network fetch, tee'd into the cache store.
+        let mut output = Vec::new();
+        {
+            let cache_writer = session.cache_writer().unwrap().unwrap();
+            let tee = CacheTee::new(&mut output, cache_writer);
+            let mut verifier = session.verifier(tee);
+            verifier.write_all(data).unwrap();
+            let tee = verifier.finish().unwrap();
+            tee.finish().unwrap();
+        }
+        session.report_success();
+        assert_eq!(output, data);
+
+        // A fresh session against the same store now gets a cache hit and
+        // never needs to touch the network.
+        let mut session2 = FetchSession::builder("sha256", &hash, &["http://src"])
+            .unwrap()
+            .cache_store(FsCacheStore::new(&dir))
+            .build()
+            .unwrap();
+
+        let cache_attempt2 = session2.next_attempt().unwrap();
+        assert_eq!(cache_attempt2.kind(), AttemptKind::Cache);
+        let mut cached = Vec::new();
+        session2
+            .check_cache()
+            .unwrap()
+            .unwrap()
+            .read_to_end(&mut cached)
+            .unwrap();
+        assert_eq!(cached, data);
+        session2.report_success();
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_next_batch_biases_servers_before_direct() {
+        let hash = sha256_hex(b"test");
+        unsafe { std::env::set_var("FETCHURL_SERVER", "\"http://cache1\", \"http://cache2\"") };
+        let mut session = FetchSession::new("sha256", &hash, &["http://src1"]).unwrap();
+
+        let batch = session.next_batch(2);
+        assert_eq!(batch.len(), 2);
+        assert_eq!(batch[0].kind(), AttemptKind::Server);
+        assert_eq!(batch[1].kind(), AttemptKind::Server);
+
+        let rest = session.next_batch(2);
+        assert_eq!(rest.len(), 1);
+        assert_eq!(rest[0].kind(), AttemptKind::Direct);
+    }
+
+    #[test]
+    fn test_report_success_for_stops_session_and_signals_cancel() {
+        let hash = sha256_hex(b"test");
+        unsafe { std::env::set_var("FETCHURL_SERVER", "") };
+        let mut session = FetchSession::new("sha256", &hash, &["http://src1", "http://src2"]).unwrap();
+
+        let batch = session.next_batch(2);
+        assert_eq!(batch.len(), 2);
+        assert_eq!(session.in_flight_count(), 2);
+        assert!(!session.should_cancel(batch[0].id()));
+        assert!(!session.should_cancel(batch[1].id()));
+
+        session.report_success_for(batch[0].id());
+        assert!(session.succeeded());
+        assert_eq!(session.in_flight_count(), 1);
+        // The winner is no longer tracked as in-flight, so it shouldn't
+        // need cancelling; the loser is still outstanding and should be.
+        assert!(!session.should_cancel(batch[0].id()));
+        assert!(session.should_cancel(batch[1].id()));
+
+        session.report_failure_for(batch[1].id());
+        assert_eq!(session.in_flight_count(), 0);
+        assert!(!session.should_cancel(batch[1].id()));
+    }
+
+    #[test]
+    fn test_server_attempt_has_signature_headers_direct_does_not() {
+        let hash = sha256_hex(b"test");
+        unsafe { std::env::set_var("FETCHURL_SERVER", "\"http://cache\"") };
+        let signer = RequestSigner::new(
+            "key1",
+            SigningKey::HmacSha256(b"secret".to_vec()),
+            vec!["@method".to_string(), "@path".to_string(), "@authority".to_string()],
+        );
+        let mut session = FetchSession::builder("sha256", &hash, &["http://src"])
+            .unwrap()
+            .request_signer(signer)
+            .build()
+            .unwrap();
+
+        let server_attempt = session.next_attempt().unwrap();
+        assert!(server_attempt.headers().iter().any(|(k, _)| k == "Signature-Input"));
+        assert!(server_attempt.headers().iter().any(|(k, _)| k == "Signature"));
+
+        let direct_attempt = session.next_attempt().unwrap();
+        assert!(!direct_attempt.headers().iter().any(|(k, _)| k == "Signature-Input"));
+    }
+
+    #[test]
+    fn test_report_redirect_resigns_server_attempt_for_new_path() {
+        let hash = sha256_hex(b"test");
+        unsafe { std::env::set_var("FETCHURL_SERVER", "\"http://cache\"") };
+        let signer = RequestSigner::new(
+            "key1",
+            SigningKey::HmacSha256(b"secret".to_vec()),
+            vec!["@method".to_string(), "@path".to_string(), "@authority".to_string()],
+        );
+        let mut session = FetchSession::builder("sha256", &hash, &[] as &[&str])
+            .unwrap()
+            .request_signer(signer)
+            .build()
+            .unwrap();
+
+        let first = session.next_attempt().unwrap();
+        let original_signature = first
+            .headers()
+            .iter()
+            .find(|(k, _)| k == "Signature")
+            .map(|(_, v)| v.clone())
+            .unwrap();
+
+        session
+            .report_redirect(first.id(), "/api/fetchurl/sha256/different-path")
+            .unwrap();
+        let redirected = session.next_attempt().unwrap();
+        let redirected_signature = redirected
+            .headers()
+            .iter()
+            .find(|(k, _)| k == "Signature")
+            .map(|(_, v)| v.clone())
+            .unwrap();
+
+        // The redirected attempt covers a different @path, so it must carry
+        // a freshly computed signature rather than the stale one.
+        assert_ne!(original_signature, redirected_signature);
+        assert_eq!(
+            redirected
+                .headers()
+                .iter()
+                .filter(|(k, _)| k == "Signature")
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_report_redirect_resolves_by_id_not_position_when_racing() {
+        let hash = sha256_hex(b"test");
+        unsafe { std::env::set_var("FETCHURL_SERVER", "") };
+        let mut session = FetchSession::new(
+            "sha256",
+            &hash,
+            &["https://src-a.example.com/file", "https://src-b.example.com/file"],
+        )
+        .unwrap();
+
+        let batch = session.next_batch(2);
+        assert_eq!(batch.len(), 2);
+        let (a, b) = (&batch[0], &batch[1]);
+
+        // Report a redirect for the *second* racing attempt. `self.current`
+        // has already advanced past both, so a positional lookup would
+        // wrongly resolve this against whichever attempt happened to be
+        // "most recent" rather than `b` itself.
+        session
+            .report_redirect(b.id(), "/moved/file")
+            .unwrap();
+
+        let next = session.next_attempt().unwrap();
+        let expected_host = url_host(b.url()).unwrap();
+        assert_eq!(next.url(), format!("https://{expected_host}/moved/file"));
+        // The untouched attempt `a` must not have been affected.
+        assert_ne!(a.url(), next.url());
+    }
 }
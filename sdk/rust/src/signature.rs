@@ -0,0 +1,206 @@
+//! HTTP Message Signatures (RFC 9421) for authenticating to cache servers.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ed25519_dalek::Signer as _;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::encode_sfv_byte_sequence;
+
+/// The key material backing a [`RequestSigner`].
+pub enum SigningKey {
+    /// Ed25519 private key (`alg="ed25519"`).
+    Ed25519(Box<ed25519_dalek::SigningKey>),
+    /// Shared HMAC-SHA256 secret (`alg="hmac-sha256"`).
+    HmacSha256(Vec<u8>),
+}
+
+impl SigningKey {
+    fn alg(&self) -> &'static str {
+        match self {
+            SigningKey::Ed25519(_) => "ed25519",
+            SigningKey::HmacSha256(_) => "hmac-sha256",
+        }
+    }
+
+    fn sign(&self, base: &[u8]) -> Vec<u8> {
+        match self {
+            SigningKey::Ed25519(key) => key.sign(base).to_bytes().to_vec(),
+            SigningKey::HmacSha256(secret) => {
+                let mut mac = Hmac::<Sha256>::new_from_slice(secret)
+                    .expect("HMAC accepts a key of any length");
+                mac.update(base);
+                mac.finalize().into_bytes().to_vec()
+            }
+        }
+    }
+}
+
+/// Signs cache-server requests per RFC 9421, producing `Signature-Input`
+/// and `Signature` headers.
+///
+/// Only attach these to fetchurl cache-server attempts — never to direct
+/// source URLs, which have no notion of this protocol.
+pub struct RequestSigner {
+    key_id: String,
+    key: SigningKey,
+    covered_components: Vec<String>,
+}
+
+impl RequestSigner {
+    /// Create a signer for `key_id` covering `covered_components` (e.g.
+    /// `["@method", "@path", "@authority", "x-source-urls"]`). Component
+    /// names are lowercased to match the RFC 9421 requirement.
+    pub fn new(
+        key_id: impl Into<String>,
+        key: SigningKey,
+        covered_components: Vec<String>,
+    ) -> Self {
+        RequestSigner {
+            key_id: key_id.into(),
+            key,
+            covered_components: covered_components
+                .into_iter()
+                .map(|c| c.to_ascii_lowercase())
+                .collect(),
+        }
+    }
+
+    /// Sign a `GET {path}` request to `authority`. `headers` supplies the
+    /// values for any covered component that isn't a derived component
+    /// (`@method`/`@path`/`@authority`).
+    ///
+    /// A covered component that isn't a derived component and has no
+    /// matching entry in `headers` is silently dropped from the signature —
+    /// it isn't actually sent, so signing it (even as an empty value) would
+    /// produce a `Signature-Input` that doesn't describe the real request.
+    ///
+    /// Returns `(Signature-Input, Signature)` header values, both prefixed
+    /// with the `sig1` label.
+    pub fn sign(&self, authority: &str, path: &str, headers: &[(String, String)]) -> (String, String) {
+        let created = unix_timestamp();
+
+        let covered: Vec<(&str, String)> = self
+            .covered_components
+            .iter()
+            .filter_map(|component| {
+                let value = match component.as_str() {
+                    "@method" => "GET".to_string(),
+                    "@path" => path.to_string(),
+                    "@authority" => authority.to_string(),
+                    name => headers
+                        .iter()
+                        .find(|(k, _)| k.eq_ignore_ascii_case(name))
+                        .map(|(_, v)| v.clone())?,
+                };
+                Some((component.as_str(), value))
+            })
+            .collect();
+
+        let component_list = covered
+            .iter()
+            .map(|(c, _)| format!("\"{c}\""))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let params = format!(
+            "({component_list});created={created};keyid=\"{}\";alg=\"{}\"",
+            self.key_id,
+            self.key.alg()
+        );
+
+        let mut lines: Vec<String> = covered
+            .iter()
+            .map(|(component, value)| format!("\"{component}\": {value}"))
+            .collect();
+        lines.push(format!("\"@signature-params\": {params}"));
+        let base = lines.join("\n");
+
+        let signature = self.key.sign(base.as_bytes());
+
+        (
+            format!("sig1={params}"),
+            format!("sig1={}", encode_sfv_byte_sequence(&signature)),
+        )
+    }
+}
+
+fn unix_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("system clock is after 1970")
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use ed25519_dalek::Verifier;
+
+    use super::*;
+
+    #[test]
+    fn test_hmac_signature_is_deterministic() {
+        let signer = RequestSigner::new(
+            "key1",
+            SigningKey::HmacSha256(b"shared-secret".to_vec()),
+            vec!["@method".to_string(), "@path".to_string(), "@authority".to_string()],
+        );
+        let (input_a, sig_a) = signer.sign("cache.example.com", "/api/fetchurl/sha256/abc", &[]);
+        let (_input_b, sig_b) = signer.sign("cache.example.com", "/api/fetchurl/sha256/abc", &[]);
+
+        assert_eq!(sig_a, sig_b);
+        assert!(input_a.contains("keyid=\"key1\""));
+        assert!(input_a.contains("alg=\"hmac-sha256\""));
+        assert!(input_a.starts_with("sig1=(\"@method\" \"@path\" \"@authority\")"));
+    }
+
+    #[test]
+    fn test_ed25519_signature_verifies() {
+        let mut rng = rand::rngs::OsRng;
+        let signing_key = ed25519_dalek::SigningKey::generate(&mut rng);
+        let verifying_key = signing_key.verifying_key();
+
+        let signer = RequestSigner::new(
+            "key1",
+            SigningKey::Ed25519(Box::new(signing_key)),
+            vec![
+                "@method".to_string(),
+                "@path".to_string(),
+                "@authority".to_string(),
+                "x-source-urls".to_string(),
+            ],
+        );
+        let headers = vec![("X-Source-Urls".to_string(), "\"https://a.com\"".to_string())];
+        let (input, sig) = signer.sign("cache.example.com", "/api/fetchurl/sha256/abc", &headers);
+
+        // Rebuild the exact base the signer used and check it verifies.
+        let params = input.strip_prefix("sig1=").unwrap();
+        let base = format!(
+            "\"@method\": GET\n\"@path\": /api/fetchurl/sha256/abc\n\"@authority\": cache.example.com\n\"x-source-urls\": \"https://a.com\"\n\"@signature-params\": {params}"
+        );
+        let raw_sig = crate::parse_sfv_byte_sequence(sig.strip_prefix("sig1=").unwrap()).unwrap();
+        let signature = ed25519_dalek::Signature::from_slice(&raw_sig).unwrap();
+        assert!(verifying_key.verify(base.as_bytes(), &signature).is_ok());
+    }
+
+    #[test]
+    fn test_sign_drops_covered_header_with_no_matching_value() {
+        let signer = RequestSigner::new(
+            "key1",
+            SigningKey::HmacSha256(b"shared-secret".to_vec()),
+            vec![
+                "@method".to_string(),
+                "@path".to_string(),
+                "@authority".to_string(),
+                "x-source-urls".to_string(),
+            ],
+        );
+
+        // No `X-Source-Urls` header present — the covered component must be
+        // dropped rather than signed as an empty value.
+        let (input, _sig) = signer.sign("cache.example.com", "/api/fetchurl/sha256/abc", &[]);
+
+        assert!(input.starts_with("sig1=(\"@method\" \"@path\" \"@authority\")"));
+        assert!(!input.contains("x-source-urls"));
+    }
+}